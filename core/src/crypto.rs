@@ -1,29 +1,183 @@
-use aes_gcm::{
-    aead::{Aead, AeadCore, KeyInit, OsRng},
-    Aes256Gcm, Key, Nonce
-};
+use aes_gcm::{aead::Aead, Aes256Gcm, Key as AesKey};
+use aes_gcm::aead::{KeyInit, OsRng};
+use chacha20poly1305::ChaCha20Poly1305;
 use argon2::Argon2;
 use anyhow::{Result, anyhow};
 use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+/// Size of each plaintext chunk in streaming mode.
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Length of the per-file random nonce prefix used in streaming mode.
+pub const STREAM_NONCE_PREFIX_LEN: usize = 7;
+
+/// Which AEAD cipher protects a file's content. Stored in the header as a
+/// single byte so files are self-describing and old files (which predate
+/// this byte) default to `Aes256Gcm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CipherId {
+    #[default]
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl CipherId {
+    pub fn to_byte(self) -> u8 {
+        match self {
+            CipherId::Aes256Gcm => 0,
+            CipherId::ChaCha20Poly1305 => 1,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(CipherId::Aes256Gcm),
+            1 => Ok(CipherId::ChaCha20Poly1305),
+            other => Err(anyhow!("Unknown cipher ID: {}", other)),
+        }
+    }
+}
+
+/// Which KDF derived a file's key from its password. Stored alongside
+/// `CipherId` so the Argon2 parameters that follow it in the header are
+/// unambiguous even if a second KDF is added later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KdfId {
+    #[default]
+    Argon2id,
+}
+
+impl KdfId {
+    pub fn to_byte(self) -> u8 {
+        match self {
+            KdfId::Argon2id => 0,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(KdfId::Argon2id),
+            other => Err(anyhow!("Unknown KDF ID: {}", other)),
+        }
+    }
+}
+
+/// Argon2id cost parameters. Encoded into the header (see `format::write_agile_header`)
+/// so a file keeps decrypting correctly even if these defaults change later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Params {
+    fn default() -> Self {
+        Self { memory_kib: 65536, iterations: 3, parallelism: 4 }
+    }
+}
+
+/// The AEAD cipher behind an `Encryptor`, selected by `CipherId`. Both
+/// variants use a 12-byte nonce and a 16-byte tag, so the STREAM framing
+/// and the wrapped-master-key format are identical regardless of which one
+/// is in use. `Aes256Gcm` is boxed since its round-key schedule is much
+/// larger than `ChaCha20Poly1305`'s state, and an unboxed enum would size
+/// every `Encryptor` to its largest variant.
+enum CipherBackend {
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+}
+
+impl CipherBackend {
+    fn new(cipher_id: CipherId, key: &[u8; 32]) -> Self {
+        match cipher_id {
+            CipherId::Aes256Gcm => CipherBackend::Aes256Gcm(Box::new(Aes256Gcm::new(
+                AesKey::<Aes256Gcm>::from_slice(key),
+            ))),
+            CipherId::ChaCha20Poly1305 => CipherBackend::ChaCha20Poly1305(ChaCha20Poly1305::new(
+                chacha20poly1305::Key::from_slice(key),
+            )),
+        }
+    }
+
+    fn encrypt(&self, nonce_bytes: &[u8; 12], plaintext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            CipherBackend::Aes256Gcm(cipher) => {
+                cipher.encrypt(aes_gcm::Nonce::from_slice(nonce_bytes), plaintext)
+            }
+            CipherBackend::ChaCha20Poly1305(cipher) => {
+                cipher.encrypt(chacha20poly1305::Nonce::from_slice(nonce_bytes), plaintext)
+            }
+        }
+        .map_err(|e| anyhow!("Encryption failed: {}", e))
+    }
+
+    fn decrypt(&self, nonce_bytes: &[u8; 12], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            CipherBackend::Aes256Gcm(cipher) => {
+                cipher.decrypt(aes_gcm::Nonce::from_slice(nonce_bytes), ciphertext)
+            }
+            CipherBackend::ChaCha20Poly1305(cipher) => {
+                cipher.decrypt(chacha20poly1305::Nonce::from_slice(nonce_bytes), ciphertext)
+            }
+        }
+        .map_err(|e| anyhow!("Decryption failed: {}", e))
+    }
+}
 
 pub struct Encryptor {
-    cipher: Aes256Gcm,
+    cipher: CipherBackend,
+}
+
+/// Build the 12-byte STREAM nonce for chunk `index`: `prefix || index (BE u32) || last_flag`.
+fn stream_nonce(prefix: &[u8; STREAM_NONCE_PREFIX_LEN], index: u32, is_last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..7].copy_from_slice(prefix);
+    nonce[7..11].copy_from_slice(&index.to_be_bytes());
+    nonce[11] = if is_last { 1 } else { 0 };
+    nonce
+}
+
+/// Fill `buf` by repeatedly calling `read`, stopping at EOF. Returns the
+/// number of bytes actually filled, which is less than `buf.len()` only
+/// at the end of the stream.
+fn fill_chunk<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = reader.read(&mut buf[filled..])?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
 }
 
 impl Encryptor {
-    /// Derive a key from password using Argon2
+    /// Derive a key from password using Argon2id with the default cost parameters.
     pub fn derive_key_from_password(password: &str, salt: &[u8]) -> Result<[u8; 32]> {
+        Self::derive_key_from_password_with_params(password, salt, &Argon2Params::default())
+    }
+
+    /// Derive a key from password using Argon2id with explicit cost parameters.
+    pub fn derive_key_from_password_with_params(
+        password: &str,
+        salt: &[u8],
+        params: &Argon2Params,
+    ) -> Result<[u8; 32]> {
         use argon2::{Algorithm, Params, Version};
-        
-        let params = Params::new(65536, 3, 4, Some(32))
+
+        let params = Params::new(params.memory_kib, params.iterations, params.parallelism, Some(32))
             .map_err(|e| anyhow!("Failed to create Argon2 parameters: {:?}", e))?;
-        
+
         let argon2 = Argon2::new(
             Algorithm::Argon2id,
             Version::V0x13,
             params
         );
-        
+
         let mut key = [0u8; 32];
         argon2.hash_password_into(
             password.as_bytes(),
@@ -31,53 +185,323 @@ impl Encryptor {
             &mut key
         )
         .map_err(|e| anyhow!("Failed to derive key from password: {:?}", e))?;
-        
+
         Ok(key)
     }
-    
-    /// Create a new encryptor with a password
+
+    /// Create a new encryptor with a password, using AES-256-GCM and the
+    /// default Argon2id parameters.
     pub fn new_with_password(password: &str, salt: &[u8]) -> Result<Self> {
-        let key = Self::derive_key_from_password(password, salt)?;
-        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
-        
-        Ok(Self { cipher })
+        Self::new_with_password_and_suite(password, salt, CipherId::default(), &Argon2Params::default())
+    }
+
+    /// Create a new encryptor with a password, a chosen cipher suite, and
+    /// explicit Argon2id parameters.
+    pub fn new_with_password_and_suite(
+        password: &str,
+        salt: &[u8],
+        cipher_id: CipherId,
+        params: &Argon2Params,
+    ) -> Result<Self> {
+        let key = Self::derive_key_from_password_with_params(password, salt, params)?;
+        Ok(Self { cipher: CipherBackend::new(cipher_id, &key) })
     }
-    
+
     /// Encrypt data
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
-        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
-        let ciphertext = self.cipher
-            .encrypt(&nonce, plaintext)
-            .map_err(|e| anyhow!("Encryption failed: {}", e))?;
-        
+        let mut nonce_bytes = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let ciphertext = self.cipher.encrypt(&nonce_bytes, plaintext)?;
+
         // Prepend nonce to ciphertext
-        let mut result = nonce.to_vec();
+        let mut result = nonce_bytes.to_vec();
+        result.extend_from_slice(&ciphertext);
+
+        Ok(result)
+    }
+
+    /// Deterministically encrypt `plaintext`: the nonce is derived from a
+    /// hash of the plaintext instead of drawn from the RNG, so the same
+    /// plaintext under the same key always produces the same ciphertext.
+    /// Used for filename encryption (see `filenames.rs`), where two files
+    /// sharing a parent directory must land under the same encrypted
+    /// directory name rather than each growing its own private copy of the
+    /// directory tree.
+    pub fn encrypt_deterministic(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let hash = Sha256::digest(plaintext);
+        let nonce_bytes: [u8; 12] = hash[..12].try_into().expect("SHA-256 digest is 32 bytes");
+        let ciphertext = self.cipher.encrypt(&nonce_bytes, plaintext)?;
+
+        let mut result = nonce_bytes.to_vec();
         result.extend_from_slice(&ciphertext);
-        
+
         Ok(result)
     }
-    
+
     /// Decrypt data
     pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
         if ciphertext.len() < 12 {
             return Err(anyhow!("Invalid ciphertext: too short"));
         }
-        
+
         // Extract nonce and actual ciphertext
         let (nonce_bytes, encrypted_data) = ciphertext.split_at(12);
-        let nonce = Nonce::from_slice(nonce_bytes);
-        
-        let plaintext = self.cipher
-            .decrypt(nonce, encrypted_data)
-            .map_err(|e| anyhow!("Decryption failed: {}", e))?;
-        
-        Ok(plaintext)
-    }
-    
+        let nonce: [u8; 12] = nonce_bytes.try_into().expect("split_at(12) guarantees length 12");
+
+        self.cipher.decrypt(&nonce, encrypted_data)
+    }
+
     /// Generate a random salt
     pub fn generate_salt() -> [u8; 32] {
         let mut salt = [0u8; 32];
         OsRng.fill_bytes(&mut salt);
         salt
     }
+
+    /// Generate a random per-file nonce prefix for streaming encryption
+    pub fn generate_stream_nonce_prefix() -> [u8; STREAM_NONCE_PREFIX_LEN] {
+        let mut prefix = [0u8; STREAM_NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut prefix);
+        prefix
+    }
+
+    /// Build an encryptor directly from a raw 256-bit key, bypassing Argon2.
+    /// Used to encrypt/decrypt file content with a random master key instead
+    /// of a key derived straight from the password. Uses AES-256-GCM.
+    pub fn from_master_key(master_key: &[u8; 32]) -> Self {
+        Self::from_master_key_and_suite(master_key, CipherId::default())
+    }
+
+    /// Like `from_master_key`, but with an explicit cipher suite.
+    pub fn from_master_key_and_suite(master_key: &[u8; 32], cipher_id: CipherId) -> Self {
+        Self { cipher: CipherBackend::new(cipher_id, master_key) }
+    }
+
+    /// Generate a random 256-bit master key.
+    pub fn generate_master_key() -> [u8; 32] {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        key
+    }
+
+    /// Wrap `master_key` under this encryptor's key (typically a
+    /// password-derived key-encryption-key), returning `nonce || ciphertext+tag`.
+    pub fn wrap_master_key(&self, master_key: &[u8; 32]) -> Result<Vec<u8>> {
+        self.encrypt(master_key)
+    }
+
+    /// Reverse `wrap_master_key`.
+    pub fn unwrap_master_key(&self, wrapped_master_key: &[u8]) -> Result<[u8; 32]> {
+        let bytes = self.decrypt(wrapped_master_key)
+            .map_err(|e| anyhow!("Failed to unwrap master key: {}", e))?;
+        bytes
+            .try_into()
+            .map_err(|_| anyhow!("Invalid wrapped master key: unexpected length"))
+    }
+
+    /// Encrypt `reader` as a sequence of `STREAM_CHUNK_SIZE` plaintext chunks,
+    /// writing `[len: u32 BE][ciphertext+tag]` records to `writer`.
+    ///
+    /// Each chunk is encrypted independently with a nonce derived from
+    /// `nonce_prefix`, the chunk index, and whether it is the final chunk
+    /// (the STREAM construction), so the whole file never has to be held
+    /// in memory and a truncated stream fails to authenticate instead of
+    /// silently decrypting short.
+    pub fn encrypt_stream<R: Read, W: Write>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        nonce_prefix: &[u8; STREAM_NONCE_PREFIX_LEN],
+    ) -> Result<()> {
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        let mut next_buf = vec![0u8; STREAM_CHUNK_SIZE];
+
+        let mut len = fill_chunk(reader, &mut buf)?;
+        let mut index: u32 = 0;
+
+        loop {
+            let next_len = fill_chunk(reader, &mut next_buf)?;
+            let is_last = next_len == 0;
+
+            let nonce_bytes = stream_nonce(nonce_prefix, index, is_last);
+            let ciphertext = self.cipher.encrypt(&nonce_bytes, &buf[..len])?;
+
+            writer.write_all(&(ciphertext.len() as u32).to_be_bytes())?;
+            writer.write_all(&ciphertext)?;
+
+            if is_last {
+                break;
+            }
+
+            std::mem::swap(&mut buf, &mut next_buf);
+            len = next_len;
+            index += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Decrypt a single previously-encrypted chunk at `index`, without
+    /// needing the rest of the stream. Used by random-access readers
+    /// (e.g. the FUSE mount) that only need the chunks covering a
+    /// requested byte range.
+    pub fn decrypt_chunk(
+        &self,
+        ciphertext: &[u8],
+        nonce_prefix: &[u8; STREAM_NONCE_PREFIX_LEN],
+        index: u32,
+        is_last: bool,
+    ) -> Result<Vec<u8>> {
+        let nonce_bytes = stream_nonce(nonce_prefix, index, is_last);
+        self.cipher.decrypt(&nonce_bytes, ciphertext)
+    }
+
+    /// Decrypt a stream produced by `encrypt_stream`, writing plaintext
+    /// chunks to `writer` as soon as each record is authenticated.
+    pub fn decrypt_stream<R: Read, W: Write>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        nonce_prefix: &[u8; STREAM_NONCE_PREFIX_LEN],
+    ) -> Result<()> {
+        let mut index: u32 = 0;
+        let mut pending_len_bytes: Option<[u8; 4]> = None;
+
+        loop {
+            let len_bytes = match pending_len_bytes.take() {
+                Some(b) => b,
+                None => {
+                    let mut b = [0u8; 4];
+                    let n = fill_chunk(reader, &mut b)?;
+                    if n == 0 {
+                        return Err(anyhow!("Truncated stream: missing final chunk"));
+                    }
+                    if n != 4 {
+                        return Err(anyhow!("Truncated stream: incomplete chunk length"));
+                    }
+                    b
+                }
+            };
+
+            let record_len = u32::from_be_bytes(len_bytes) as usize;
+            let mut record = vec![0u8; record_len];
+            let read = fill_chunk(reader, &mut record)?;
+            if read != record_len {
+                return Err(anyhow!("Truncated stream: incomplete chunk data"));
+            }
+
+            let mut next_len_bytes = [0u8; 4];
+            let peeked = fill_chunk(reader, &mut next_len_bytes)?;
+            let is_last = peeked == 0;
+            if !is_last {
+                if peeked != 4 {
+                    return Err(anyhow!("Truncated stream: incomplete chunk length"));
+                }
+                pending_len_bytes = Some(next_len_bytes);
+            }
+
+            let nonce_bytes = stream_nonce(nonce_prefix, index, is_last);
+            let plaintext = self.cipher
+                .decrypt(&nonce_bytes, record.as_slice())
+                .map_err(|e| anyhow!("Decryption failed: last chunk truncated or corrupted: {}", e))?;
+            writer.write_all(&plaintext)?;
+
+            if is_last {
+                break;
+            }
+            index += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_encryptor() -> Encryptor {
+        Encryptor::new_with_password("password", &[0u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn streams_round_trip_an_empty_file() {
+        let encryptor = test_encryptor();
+        let prefix = Encryptor::generate_stream_nonce_prefix();
+
+        let mut ciphertext = Vec::new();
+        encryptor.encrypt_stream(&mut [].as_slice(), &mut ciphertext, &prefix).unwrap();
+
+        let mut plaintext = Vec::new();
+        encryptor.decrypt_stream(&mut ciphertext.as_slice(), &mut plaintext, &prefix).unwrap();
+        assert!(plaintext.is_empty());
+    }
+
+    #[test]
+    fn streams_round_trip_several_chunks() {
+        let encryptor = test_encryptor();
+        let prefix = Encryptor::generate_stream_nonce_prefix();
+
+        // A couple chunks plus a partial final one, to exercise the last_flag
+        // handling across a chunk boundary.
+        let data = vec![0xABu8; STREAM_CHUNK_SIZE * 2 + 17];
+
+        let mut ciphertext = Vec::new();
+        encryptor.encrypt_stream(&mut data.as_slice(), &mut ciphertext, &prefix).unwrap();
+
+        let mut plaintext = Vec::new();
+        encryptor.decrypt_stream(&mut ciphertext.as_slice(), &mut plaintext, &prefix).unwrap();
+        assert_eq!(plaintext, data);
+    }
+
+    #[test]
+    fn dropping_the_final_chunk_fails_to_authenticate() {
+        let encryptor = test_encryptor();
+        let prefix = Encryptor::generate_stream_nonce_prefix();
+        let data = vec![0x42u8; STREAM_CHUNK_SIZE + 1];
+
+        let mut ciphertext = Vec::new();
+        encryptor.encrypt_stream(&mut data.as_slice(), &mut ciphertext, &prefix).unwrap();
+
+        // Drop the last record (length prefix + ciphertext+tag) so the
+        // decoder only sees a chunk that was authenticated with last_flag=0.
+        let first_record_len = u32::from_be_bytes(ciphertext[0..4].try_into().unwrap()) as usize;
+        let truncated = &ciphertext[..4 + first_record_len];
+
+        let mut plaintext = Vec::new();
+        assert!(encryptor.decrypt_stream(&mut &truncated[..], &mut plaintext, &prefix).is_err());
+    }
+
+    #[test]
+    fn wraps_and_unwraps_a_master_key() {
+        let kek = test_encryptor();
+        let master_key = Encryptor::generate_master_key();
+
+        let wrapped = kek.wrap_master_key(&master_key).unwrap();
+        assert_eq!(kek.unwrap_master_key(&wrapped).unwrap(), master_key);
+    }
+
+    #[test]
+    fn content_round_trips_under_each_cipher_suite() {
+        for cipher_id in [CipherId::Aes256Gcm, CipherId::ChaCha20Poly1305] {
+            let master_key = Encryptor::generate_master_key();
+            let encryptor = Encryptor::from_master_key_and_suite(&master_key, cipher_id);
+
+            let ciphertext = encryptor.encrypt(b"hello world").unwrap();
+            assert_eq!(encryptor.decrypt(&ciphertext).unwrap(), b"hello world");
+        }
+    }
+
+    #[test]
+    fn deterministic_encryption_is_stable_and_round_trips() {
+        let encryptor = test_encryptor();
+
+        let first = encryptor.encrypt_deterministic(b"docs").unwrap();
+        let second = encryptor.encrypt_deterministic(b"docs").unwrap();
+        assert_eq!(first, second, "same plaintext under the same key must produce the same ciphertext");
+        assert_eq!(encryptor.decrypt(&first).unwrap(), b"docs");
+
+        let different = encryptor.encrypt_deterministic(b"taxes").unwrap();
+        assert_ne!(first, different);
+    }
 }
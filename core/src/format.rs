@@ -1,4 +1,183 @@
+use std::io::{Read, Write};
 use anyhow::{Result, anyhow};
+use crate::crypto::Argon2Params;
+
+/// Single-shot, whole-file-in-memory container.
+pub const VERSION_1: u8 = 1;
+/// Streamed, chunk-by-chunk container (see `crypto::Encryptor::encrypt_stream`).
+pub const VERSION_STREAMED: u8 = 2;
+/// Streamed container whose content key is a random master key, itself
+/// wrapped under a password-derived key-encryption-key (see
+/// `crypto::Encryptor::wrap_master_key`).
+pub const VERSION_MASTER_KEY: u8 = 3;
+/// Streamed, master-keyed container that additionally records which
+/// cipher/KDF protect it and the Argon2 parameters used, so the file is
+/// self-describing and algorithm choices can vary per file.
+pub const VERSION_AGILE: u8 = 4;
+
+/// Length of the random nonce prefix stored in a streamed header.
+const STREAM_NONCE_PREFIX_LEN: usize = 7;
+
+/// Length of a wrapped master key: 12-byte nonce + 32-byte key + 16-byte tag.
+pub const WRAPPED_MASTER_KEY_LEN: usize = 12 + 32 + 16;
+
+/// Header of a parsed `ENCR` container.
+pub struct ParsedHeader {
+    pub salt: [u8; 32],
+    /// `Some` for version-2/3/4 (streamed) files, carrying the per-file nonce prefix.
+    pub stream_nonce_prefix: Option<[u8; 7]>,
+    /// `Some` for version-3/4 (master-key) files: `nonce || wrapped_key+tag`,
+    /// ready to be passed to `Encryptor::unwrap_master_key`.
+    pub wrapped_master_key: Option<Vec<u8>>,
+    /// `crypto::CipherId` byte. Defaults to `0` (AES-256-GCM) for files
+    /// older than version 4, which predate algorithm agility.
+    pub cipher_id: u8,
+    /// `crypto::KdfId` byte. Defaults to `0` (Argon2id) for files older
+    /// than version 4.
+    pub kdf_id: u8,
+    /// `(memory_kib, iterations, parallelism)`. Defaults to
+    /// `Argon2Params::default()` for files older than version 4.
+    pub argon2_params: (u32, u32, u32),
+}
+
+/// Write a version-1 (whole-file) header: magic, version, salt.
+pub fn write_header<W: Write>(writer: &mut W, salt: &[u8; 32]) -> Result<()> {
+    writer.write_all(b"ENCR")?;
+    writer.write_all(&[VERSION_1])?;
+    writer.write_all(salt)?;
+    Ok(())
+}
+
+/// Write a version-2 (streamed) header: magic, version, salt, nonce prefix.
+pub fn write_streamed_header<W: Write>(
+    writer: &mut W,
+    salt: &[u8; 32],
+    nonce_prefix: &[u8; 7],
+) -> Result<()> {
+    writer.write_all(b"ENCR")?;
+    writer.write_all(&[VERSION_STREAMED])?;
+    writer.write_all(salt)?;
+    writer.write_all(nonce_prefix)?;
+    Ok(())
+}
+
+/// Write a version-3 (master-key) header: magic, version, salt, wrapped
+/// master key, nonce prefix. `wrapped_master_key` must be
+/// `WRAPPED_MASTER_KEY_LEN` bytes, as produced by `Encryptor::wrap_master_key`.
+pub fn write_master_key_header<W: Write>(
+    writer: &mut W,
+    salt: &[u8; 32],
+    wrapped_master_key: &[u8],
+    nonce_prefix: &[u8; 7],
+) -> Result<()> {
+    if wrapped_master_key.len() != WRAPPED_MASTER_KEY_LEN {
+        return Err(anyhow!("Invalid wrapped master key length"));
+    }
+    writer.write_all(b"ENCR")?;
+    writer.write_all(&[VERSION_MASTER_KEY])?;
+    writer.write_all(salt)?;
+    writer.write_all(wrapped_master_key)?;
+    writer.write_all(nonce_prefix)?;
+    Ok(())
+}
+
+/// Write a version-4 (algorithm-agile) header: magic, version, cipher ID,
+/// KDF ID, Argon2 params (memory/iterations/parallelism as big-endian u32s),
+/// salt, wrapped master key, nonce prefix. `cipher_id`/`kdf_id` are
+/// `crypto::CipherId`/`crypto::KdfId` bytes; `wrapped_master_key` must be
+/// `WRAPPED_MASTER_KEY_LEN` bytes, produced under the same cipher.
+pub fn write_agile_header<W: Write>(
+    writer: &mut W,
+    cipher_id: u8,
+    kdf_id: u8,
+    argon2_params: (u32, u32, u32),
+    salt: &[u8; 32],
+    wrapped_master_key: &[u8],
+    nonce_prefix: &[u8; 7],
+) -> Result<()> {
+    if wrapped_master_key.len() != WRAPPED_MASTER_KEY_LEN {
+        return Err(anyhow!("Invalid wrapped master key length"));
+    }
+    writer.write_all(b"ENCR")?;
+    writer.write_all(&[VERSION_AGILE])?;
+    writer.write_all(&[cipher_id, kdf_id])?;
+    writer.write_all(&argon2_params.0.to_be_bytes())?;
+    writer.write_all(&argon2_params.1.to_be_bytes())?;
+    writer.write_all(&argon2_params.2.to_be_bytes())?;
+    writer.write_all(salt)?;
+    writer.write_all(wrapped_master_key)?;
+    writer.write_all(nonce_prefix)?;
+    Ok(())
+}
+
+/// Read and validate an `ENCR` header from `reader`, leaving the reader
+/// positioned at the start of the encrypted payload (the whole-file
+/// ciphertext for version 1, or the first stream record otherwise).
+pub fn read_header<R: Read>(reader: &mut R) -> Result<ParsedHeader> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)
+        .map_err(|_| anyhow!("Invalid encrypted file: too short"))?;
+    if &magic != b"ENCR" {
+        return Err(anyhow!("Invalid encrypted file: wrong magic bytes"));
+    }
+
+    let mut version = [0u8; 1];
+    reader.read_exact(&mut version)
+        .map_err(|_| anyhow!("Invalid encrypted file: too short"))?;
+
+    // Versions before 4 predate algorithm agility and are implicitly
+    // AES-256-GCM + Argon2id with the default cost parameters.
+    let (cipher_id, kdf_id, argon2_params) = if version[0] == VERSION_AGILE {
+        let mut ids = [0u8; 2];
+        reader.read_exact(&mut ids)
+            .map_err(|_| anyhow!("Invalid encrypted file: too short"))?;
+        let mut params_bytes = [0u8; 12];
+        reader.read_exact(&mut params_bytes)
+            .map_err(|_| anyhow!("Invalid encrypted file: too short"))?;
+        let memory_kib = u32::from_be_bytes(params_bytes[0..4].try_into().unwrap());
+        let iterations = u32::from_be_bytes(params_bytes[4..8].try_into().unwrap());
+        let parallelism = u32::from_be_bytes(params_bytes[8..12].try_into().unwrap());
+        (ids[0], ids[1], (memory_kib, iterations, parallelism))
+    } else {
+        let defaults = Argon2Params::default();
+        (0u8, 0u8, (defaults.memory_kib, defaults.iterations, defaults.parallelism))
+    };
+
+    let mut salt = [0u8; 32];
+    reader.read_exact(&mut salt)
+        .map_err(|_| anyhow!("Invalid encrypted file: too short"))?;
+
+    match version[0] {
+        VERSION_1 => Ok(ParsedHeader {
+            salt, stream_nonce_prefix: None, wrapped_master_key: None, cipher_id, kdf_id, argon2_params,
+        }),
+        VERSION_STREAMED => {
+            let mut nonce_prefix = [0u8; STREAM_NONCE_PREFIX_LEN];
+            reader.read_exact(&mut nonce_prefix)
+                .map_err(|_| anyhow!("Invalid encrypted file: too short"))?;
+            Ok(ParsedHeader {
+                salt, stream_nonce_prefix: Some(nonce_prefix), wrapped_master_key: None, cipher_id, kdf_id, argon2_params,
+            })
+        }
+        VERSION_MASTER_KEY | VERSION_AGILE => {
+            let mut wrapped_master_key = vec![0u8; WRAPPED_MASTER_KEY_LEN];
+            reader.read_exact(&mut wrapped_master_key)
+                .map_err(|_| anyhow!("Invalid encrypted file: too short"))?;
+            let mut nonce_prefix = [0u8; STREAM_NONCE_PREFIX_LEN];
+            reader.read_exact(&mut nonce_prefix)
+                .map_err(|_| anyhow!("Invalid encrypted file: too short"))?;
+            Ok(ParsedHeader {
+                salt,
+                stream_nonce_prefix: Some(nonce_prefix),
+                wrapped_master_key: Some(wrapped_master_key),
+                cipher_id,
+                kdf_id,
+                argon2_params,
+            })
+        }
+        _ => Err(anyhow!("Unsupported file version")),
+    }
+}
 
 /// Create encrypted file format with metadata
 pub fn create_encrypted_file(
@@ -6,19 +185,19 @@ pub fn create_encrypted_file(
     encrypted_data: &[u8],
 ) -> Vec<u8> {
     let mut result = Vec::new();
-    
+
     // Magic bytes to identify our encrypted files
     result.extend_from_slice(b"ENCR");
-    
+
     // Version byte
-    result.push(1);
-    
+    result.push(VERSION_1);
+
     // Salt (32 bytes)
     result.extend_from_slice(salt);
-    
+
     // Encrypted data
     result.extend_from_slice(encrypted_data);
-    
+
     result
 }
 
@@ -27,23 +206,23 @@ pub fn parse_encrypted_file(data: &[u8]) -> Result<([u8; 32], Vec<u8>)> {
     if data.len() < 37 {  // 4 (magic) + 1 (version) + 32 (salt)
         return Err(anyhow!("Invalid encrypted file: too short"));
     }
-    
+
     // Check magic bytes
     if &data[0..4] != b"ENCR" {
         return Err(anyhow!("Invalid encrypted file: wrong magic bytes"));
     }
-    
+
     // Check version
-    if data[4] != 1 {
+    if data[4] != VERSION_1 {
         return Err(anyhow!("Unsupported file version"));
     }
-    
+
     // Extract salt
     let mut salt = [0u8; 32];
     salt.copy_from_slice(&data[5..37]);
-    
+
     // Extract encrypted data
     let encrypted_data = data[37..].to_vec();
-    
+
     Ok((salt, encrypted_data))
 }
@@ -1,5 +1,9 @@
 pub mod crypto;
 pub mod format;
 
-pub use crypto::Encryptor;
-pub use format::{create_encrypted_file, parse_encrypted_file};
+pub use crypto::{Argon2Params, CipherId, Encryptor, KdfId};
+pub use format::{
+    create_encrypted_file, parse_encrypted_file, read_header, write_agile_header, write_header,
+    write_master_key_header, write_streamed_header, ParsedHeader, VERSION_1, VERSION_AGILE,
+    VERSION_MASTER_KEY, VERSION_STREAMED, WRAPPED_MASTER_KEY_LEN,
+};
@@ -1,4 +1,13 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Cipher suite selectable from the CLI; mirrors `encryptor_core::CipherId`.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CipherArg {
+    #[value(name = "aes256gcm")]
+    Aes256Gcm,
+    #[value(name = "chacha20poly1305")]
+    ChaCha20Poly1305,
+}
 
 #[derive(Parser)]
 #[command(name = "file-encryptor")]
@@ -15,21 +24,38 @@ pub enum Commands {
         /// Input file path
         #[arg(short, long)]
         input: String,
-        
+
         /// Output file path (optional)
         #[arg(short, long)]
         output: Option<String>,
+
+        /// Cipher suite to protect the content with
+        #[arg(long, value_enum, default_value = "aes256gcm")]
+        cipher: CipherArg,
+
+        /// Argon2id parameters as "memory_kib,iterations,parallelism" (default: 65536,3,4)
+        #[arg(long)]
+        argon2_params: Option<String>,
+
+        /// Hide the encrypted container in a carrier image's least-significant
+        /// bits instead of writing a `.encrypted` file
+        #[arg(long, value_name = "CARRIER")]
+        stego: Option<String>,
     },
-    
+
     /// Decrypt a file
     Decrypt {
         /// Input file path
         #[arg(short, long)]
         input: String,
-        
+
         /// Output file path (optional)
         #[arg(short, long)]
         output: Option<String>,
+
+        /// Treat `input` as a stego image produced by `encrypt --stego` (or `embed`)
+        #[arg(long)]
+        stego: bool,
     },
     
     /// Encrypt all files in a directory (preserves structure)
@@ -37,20 +63,80 @@ pub enum Commands {
         /// Input directory path
         #[arg(short, long)]
         input: String,
-        
+
         /// Output directory path (optional, defaults to <input>.encrypted)
         #[arg(short, long)]
         output: Option<String>,
+
+        /// Encrypt file and directory names too, instead of leaving them in the clear
+        #[arg(long)]
+        encrypt_names: bool,
+
+        /// Cipher suite to protect the content with
+        #[arg(long, value_enum, default_value = "aes256gcm")]
+        cipher: CipherArg,
+
+        /// Argon2id parameters as "memory_kib,iterations,parallelism" (default: 65536,3,4)
+        #[arg(long)]
+        argon2_params: Option<String>,
     },
-    
+
     /// Decrypt all .encrypted files in a directory (preserves structure)
     DecryptDir {
         /// Input directory path (containing .encrypted files)
         #[arg(short, long)]
         input: String,
-        
+
         /// Output directory path (optional, defaults to <input> with .encrypted stripped)
         #[arg(short, long)]
         output: Option<String>,
+
+        /// Reverse filename encryption applied with `encrypt-dir --encrypt-names`
+        #[arg(long)]
+        encrypt_names: bool,
+    },
+
+    /// Mount an encrypted directory as a transparent read/write filesystem
+    Mount {
+        /// Path to the directory holding the encrypted (`ENCR`) files
+        #[arg(short, long)]
+        input: String,
+
+        /// Directory to mount the decrypted view at
+        #[arg(short, long)]
+        mountpoint: String,
+    },
+
+    /// Change the password protecting a file or directory without re-encrypting its content
+    ChangePassword {
+        /// Path to an encrypted file, or a directory of encrypted files
+        #[arg(short, long)]
+        input: String,
+    },
+
+    /// Hide an already-encrypted container (or any file) inside a carrier image's LSBs
+    Embed {
+        /// Path to the file to hide
+        #[arg(short, long)]
+        input: String,
+
+        /// Carrier image (PNG/BMP) to hide it inside
+        #[arg(short, long)]
+        carrier: String,
+
+        /// Output stego image path (optional, defaults to `<carrier>_stego.png`)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Recover a file previously hidden with `embed` (or `encrypt --stego`)
+    Extract {
+        /// Stego image to read from
+        #[arg(short, long)]
+        input: String,
+
+        /// Output file path (optional, defaults to `<input>` with its extension replaced by `.extracted`)
+        #[arg(short, long)]
+        output: Option<String>,
     },
 }
\ No newline at end of file
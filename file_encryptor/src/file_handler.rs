@@ -1,9 +1,8 @@
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
 use anyhow::{Result, Context};
-
-const PROGRESS_CHUNK_SIZE: usize = 64 * 1024; // 64 KB
+use encryptor_core::Encryptor;
 
 pub struct FileHandler;
 
@@ -20,37 +19,6 @@ impl FileHandler {
         Ok(buffer)
     }
     
-    /// Read file in chunks, calling on_progress(bytes_read, total) for progress reporting
-    pub fn read_file_with_progress<F>(path: &Path, mut on_progress: F) -> Result<Vec<u8>>
-    where
-        F: FnMut(u64, u64),
-    {
-        let total = std::fs::metadata(path)
-            .with_context(|| format!("Failed to get file size: {}", path.display()))?
-            .len();
-        
-        let mut file = File::open(path)
-            .with_context(|| format!("Failed to open file: {}", path.display()))?;
-        
-        let mut buffer = Vec::with_capacity(total as usize);
-        let mut read = 0u64;
-        let mut chunk = vec![0u8; PROGRESS_CHUNK_SIZE];
-        
-        loop {
-            let n = file
-                .read(&mut chunk)
-                .with_context(|| format!("Failed to read file: {}", path.display()))?;
-            if n == 0 {
-                break;
-            }
-            buffer.extend_from_slice(&chunk[..n]);
-            read += n as u64;
-            on_progress(read, total);
-        }
-        
-        Ok(buffer)
-    }
-    
     /// Write data to file
     pub fn write_file(path: &Path, data: &[u8]) -> Result<()> {
         let mut file = OpenOptions::new()
@@ -66,30 +34,6 @@ impl FileHandler {
         Ok(())
     }
     
-    /// Write data in chunks, calling on_progress(bytes_written, total) for progress reporting
-    pub fn write_file_with_progress<F>(path: &Path, data: &[u8], mut on_progress: F) -> Result<()>
-    where
-        F: FnMut(u64, u64),
-    {
-        let total = data.len() as u64;
-        let mut file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(path)
-            .with_context(|| format!("Failed to create file: {}", path.display()))?;
-        
-        let mut written = 0u64;
-        for chunk in data.chunks(PROGRESS_CHUNK_SIZE) {
-            file.write_all(chunk)
-                .with_context(|| format!("Failed to write file: {}", path.display()))?;
-            written += chunk.len() as u64;
-            on_progress(written, total);
-        }
-        
-        Ok(())
-    }
-    
     /// Ensure parent directories exist for the given path
     pub fn create_parent_dirs(path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
@@ -99,16 +43,325 @@ impl FileHandler {
         Ok(())
     }
     
-    /// Create encrypted file format with metadata (delegates to core)
-    pub fn create_encrypted_file(
-        salt: &[u8],
-        encrypted_data: &[u8],
-    ) -> Vec<u8> {
-        encryptor_core::create_encrypted_file(salt, encrypted_data)
-    }
-    
     /// Parse encrypted file format (delegates to core)
     pub fn parse_encrypted_file(data: &[u8]) -> Result<([u8; 32], Vec<u8>)> {
         encryptor_core::parse_encrypted_file(data)
     }
+
+    /// Decrypt a streamed (version 2) container from `input_path` into
+    /// `output_path` without buffering the whole file.
+    pub fn decrypt_file_streamed(
+        input_path: &Path,
+        output_path: &Path,
+        password: &str,
+    ) -> Result<()> {
+        let input = File::open(input_path)
+            .with_context(|| format!("Failed to open file: {}", input_path.display()))?;
+        let mut reader = BufReader::new(input);
+
+        let header = encryptor_core::read_header(&mut reader)
+            .with_context(|| format!("Failed to parse encrypted file: {}", input_path.display()))?;
+        let nonce_prefix = header
+            .stream_nonce_prefix
+            .ok_or_else(|| anyhow::anyhow!("File is not in streamed format: {}", input_path.display()))?;
+
+        let encryptor = Encryptor::new_with_password(password, &header.salt)
+            .context("Failed to initialize decryptor")?;
+
+        let output = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(output_path)
+            .with_context(|| format!("Failed to create file: {}", output_path.display()))?;
+        let mut writer = BufWriter::new(output);
+
+        encryptor
+            .decrypt_stream(&mut reader, &mut writer, &nonce_prefix)
+            .context("Streaming decryption failed - wrong password or corrupted file")?;
+
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write file: {}", output_path.display()))?;
+
+        Ok(())
+    }
+
+    /// Read just the `ENCR` header of `path`, for dispatching between the
+    /// whole-file and streamed code paths without loading the payload.
+    pub fn read_header(path: &Path) -> Result<encryptor_core::ParsedHeader> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open file: {}", path.display()))?;
+        let mut reader = BufReader::new(file);
+        encryptor_core::read_header(&mut reader)
+            .with_context(|| format!("Failed to parse encrypted file: {}", path.display()))
+    }
+
+    /// Encrypt `input_path` into `output_path` using master-key indirection
+    /// and algorithm agility (version 4): `master_key` (shared across a
+    /// whole encrypt-dir run, or fresh per single-file encrypt) is wrapped
+    /// under a key derived from `password` and `salt` with `argon2_params`,
+    /// so the password can later be changed without touching file content,
+    /// and both the cipher and the KDF cost are recorded in the header so
+    /// the file stays self-describing.
+    pub fn encrypt_file_agile(
+        input_path: &Path,
+        output_path: &Path,
+        password: &str,
+        salt: &[u8; 32],
+        master_key: &[u8; 32],
+        cipher_id: encryptor_core::CipherId,
+        argon2_params: &encryptor_core::Argon2Params,
+    ) -> Result<()> {
+        let kek = Encryptor::new_with_password_and_suite(password, salt, cipher_id, argon2_params)
+            .context("Failed to derive key-encryption-key")?;
+        let wrapped_master_key = kek.wrap_master_key(master_key)
+            .context("Failed to wrap master key")?;
+        let content_encryptor = Encryptor::from_master_key_and_suite(master_key, cipher_id);
+
+        let input = File::open(input_path)
+            .with_context(|| format!("Failed to open file: {}", input_path.display()))?;
+        let mut reader = BufReader::new(input);
+
+        let output = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(output_path)
+            .with_context(|| format!("Failed to create file: {}", output_path.display()))?;
+        let mut writer = BufWriter::new(output);
+
+        let nonce_prefix = Encryptor::generate_stream_nonce_prefix();
+        encryptor_core::write_agile_header(
+            &mut writer,
+            cipher_id.to_byte(),
+            encryptor_core::KdfId::Argon2id.to_byte(),
+            (argon2_params.memory_kib, argon2_params.iterations, argon2_params.parallelism),
+            salt,
+            &wrapped_master_key,
+            &nonce_prefix,
+        )
+        .with_context(|| format!("Failed to write header: {}", output_path.display()))?;
+
+        content_encryptor
+            .encrypt_stream(&mut reader, &mut writer, &nonce_prefix)
+            .context("Streaming encryption failed")?;
+
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write file: {}", output_path.display()))?;
+
+        Ok(())
+    }
+
+    /// Like `encrypt_file_agile`, but returns the encrypted container as an
+    /// in-memory buffer instead of writing it to a file. Used for
+    /// steganographic embedding, where the container needs to be hidden
+    /// inside a carrier image rather than written out directly.
+    pub fn encrypt_to_container_agile(
+        input_path: &Path,
+        password: &str,
+        salt: &[u8; 32],
+        master_key: &[u8; 32],
+        cipher_id: encryptor_core::CipherId,
+        argon2_params: &encryptor_core::Argon2Params,
+    ) -> Result<Vec<u8>> {
+        let kek = Encryptor::new_with_password_and_suite(password, salt, cipher_id, argon2_params)
+            .context("Failed to derive key-encryption-key")?;
+        let wrapped_master_key = kek.wrap_master_key(master_key)
+            .context("Failed to wrap master key")?;
+        let content_encryptor = Encryptor::from_master_key_and_suite(master_key, cipher_id);
+
+        let input = File::open(input_path)
+            .with_context(|| format!("Failed to open file: {}", input_path.display()))?;
+        let mut reader = BufReader::new(input);
+
+        let mut container = Vec::new();
+        let nonce_prefix = Encryptor::generate_stream_nonce_prefix();
+        encryptor_core::write_agile_header(
+            &mut container,
+            cipher_id.to_byte(),
+            encryptor_core::KdfId::Argon2id.to_byte(),
+            (argon2_params.memory_kib, argon2_params.iterations, argon2_params.parallelism),
+            salt,
+            &wrapped_master_key,
+            &nonce_prefix,
+        )
+        .context("Failed to write header")?;
+
+        content_encryptor
+            .encrypt_stream(&mut reader, &mut container, &nonce_prefix)
+            .context("Streaming encryption failed")?;
+
+        Ok(container)
+    }
+
+    /// Decrypt an in-memory `ENCR` container (e.g. one recovered from a
+    /// stego image) and return its plaintext, dispatching across every
+    /// container version: the in-memory counterpart of
+    /// `decrypt_file_master_keyed`, `decrypt_file_streamed` and the legacy
+    /// whole-file path.
+    pub fn decrypt_container(container: &[u8], password: &str) -> Result<Vec<u8>> {
+        let mut reader = std::io::Cursor::new(container);
+        let header = encryptor_core::read_header(&mut reader)
+            .context("Failed to parse encrypted container")?;
+
+        if let Some(wrapped_master_key) = &header.wrapped_master_key {
+            let nonce_prefix = header.stream_nonce_prefix
+                .ok_or_else(|| anyhow::anyhow!("Container is not in streamed format"))?;
+            let cipher_id = encryptor_core::CipherId::from_byte(header.cipher_id)
+                .context("Unsupported cipher")?;
+            encryptor_core::KdfId::from_byte(header.kdf_id).context("Unsupported KDF")?;
+            let argon2_params = encryptor_core::Argon2Params {
+                memory_kib: header.argon2_params.0,
+                iterations: header.argon2_params.1,
+                parallelism: header.argon2_params.2,
+            };
+
+            let kek = Encryptor::new_with_password_and_suite(password, &header.salt, cipher_id, &argon2_params)
+                .context("Failed to derive key-encryption-key")?;
+            let master_key = kek.unwrap_master_key(wrapped_master_key)
+                .context("Failed to unwrap master key - wrong password or corrupted container")?;
+            let content_encryptor = Encryptor::from_master_key_and_suite(&master_key, cipher_id);
+
+            let mut plaintext = Vec::new();
+            content_encryptor
+                .decrypt_stream(&mut reader, &mut plaintext, &nonce_prefix)
+                .context("Streaming decryption failed - wrong password or corrupted container")?;
+            Ok(plaintext)
+        } else if let Some(nonce_prefix) = header.stream_nonce_prefix {
+            let encryptor = Encryptor::new_with_password(password, &header.salt)
+                .context("Failed to initialize decryptor")?;
+            let mut plaintext = Vec::new();
+            encryptor
+                .decrypt_stream(&mut reader, &mut plaintext, &nonce_prefix)
+                .context("Streaming decryption failed - wrong password or corrupted container")?;
+            Ok(plaintext)
+        } else {
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest)?;
+            let encryptor = Encryptor::new_with_password(password, &header.salt)
+                .context("Failed to initialize decryptor")?;
+            encryptor.decrypt(&rest)
+                .context("Decryption failed - wrong password or corrupted container")
+        }
+    }
+
+    /// Decrypt a master-keyed container (version 3 or the algorithm-agile
+    /// version 4) from `input_path` into `output_path`, using whichever
+    /// cipher/KDF parameters the header records (version 3 implies the
+    /// pre-agility defaults).
+    pub fn decrypt_file_master_keyed(
+        input_path: &Path,
+        output_path: &Path,
+        password: &str,
+    ) -> Result<()> {
+        let input = File::open(input_path)
+            .with_context(|| format!("Failed to open file: {}", input_path.display()))?;
+        let mut reader = BufReader::new(input);
+
+        let header = encryptor_core::read_header(&mut reader)
+            .with_context(|| format!("Failed to parse encrypted file: {}", input_path.display()))?;
+        let wrapped_master_key = header.wrapped_master_key
+            .ok_or_else(|| anyhow::anyhow!("File does not use master-key indirection: {}", input_path.display()))?;
+        let nonce_prefix = header.stream_nonce_prefix
+            .ok_or_else(|| anyhow::anyhow!("File is not in streamed format: {}", input_path.display()))?;
+        let cipher_id = encryptor_core::CipherId::from_byte(header.cipher_id)
+            .context("Unsupported cipher")?;
+        encryptor_core::KdfId::from_byte(header.kdf_id).context("Unsupported KDF")?;
+        let argon2_params = encryptor_core::Argon2Params {
+            memory_kib: header.argon2_params.0,
+            iterations: header.argon2_params.1,
+            parallelism: header.argon2_params.2,
+        };
+
+        let kek = Encryptor::new_with_password_and_suite(password, &header.salt, cipher_id, &argon2_params)
+            .context("Failed to derive key-encryption-key")?;
+        let master_key = kek.unwrap_master_key(&wrapped_master_key)
+            .context("Failed to unwrap master key - wrong password or corrupted file")?;
+        let content_encryptor = Encryptor::from_master_key_and_suite(&master_key, cipher_id);
+
+        let output = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(output_path)
+            .with_context(|| format!("Failed to create file: {}", output_path.display()))?;
+        let mut writer = BufWriter::new(output);
+
+        content_encryptor
+            .decrypt_stream(&mut reader, &mut writer, &nonce_prefix)
+            .context("Streaming decryption failed - wrong password or corrupted file")?;
+
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write file: {}", output_path.display()))?;
+
+        Ok(())
+    }
+
+    /// Rewrap `path`'s master key under `new_password`, rewriting only its
+    /// header; the (potentially huge) body is copied through unchanged, so
+    /// a password change never touches file content. The file's cipher and
+    /// Argon2 parameters are carried over unchanged.
+    pub fn change_password(path: &Path, old_password: &str, new_password: &str) -> Result<()> {
+        let input = File::open(path)
+            .with_context(|| format!("Failed to open file: {}", path.display()))?;
+        let mut reader = BufReader::new(input);
+
+        let header = encryptor_core::read_header(&mut reader)
+            .with_context(|| format!("Failed to parse encrypted file: {}", path.display()))?;
+        let wrapped_master_key = header.wrapped_master_key
+            .ok_or_else(|| anyhow::anyhow!("File does not use master-key indirection: {}", path.display()))?;
+        let nonce_prefix = header.stream_nonce_prefix
+            .ok_or_else(|| anyhow::anyhow!("File is not in streamed format: {}", path.display()))?;
+        let cipher_id = encryptor_core::CipherId::from_byte(header.cipher_id)
+            .context("Unsupported cipher")?;
+        encryptor_core::KdfId::from_byte(header.kdf_id).context("Unsupported KDF")?;
+        let argon2_params = encryptor_core::Argon2Params {
+            memory_kib: header.argon2_params.0,
+            iterations: header.argon2_params.1,
+            parallelism: header.argon2_params.2,
+        };
+
+        let old_kek = Encryptor::new_with_password_and_suite(old_password, &header.salt, cipher_id, &argon2_params)
+            .context("Failed to derive key-encryption-key")?;
+        let master_key = old_kek.unwrap_master_key(&wrapped_master_key)
+            .context("Failed to unwrap master key - wrong password?")?;
+
+        let new_salt = Encryptor::generate_salt();
+        let new_kek = Encryptor::new_with_password_and_suite(new_password, &new_salt, cipher_id, &argon2_params)
+            .context("Failed to derive new key-encryption-key")?;
+        let new_wrapped_master_key = new_kek.wrap_master_key(&master_key)
+            .context("Failed to wrap master key")?;
+
+        let tmp_path = path.with_extension("encrypted.tmp");
+        {
+            let output = File::create(&tmp_path)
+                .with_context(|| format!("Failed to create file: {}", tmp_path.display()))?;
+            let mut writer = BufWriter::new(output);
+
+            encryptor_core::write_agile_header(
+                &mut writer,
+                cipher_id.to_byte(),
+                encryptor_core::KdfId::Argon2id.to_byte(),
+                (argon2_params.memory_kib, argon2_params.iterations, argon2_params.parallelism),
+                &new_salt,
+                &new_wrapped_master_key,
+                &nonce_prefix,
+            )
+            .with_context(|| format!("Failed to write header: {}", tmp_path.display()))?;
+
+            // Copy the (unmodified) streamed body through verbatim.
+            std::io::copy(&mut reader, &mut writer)
+                .with_context(|| format!("Failed to copy file body: {}", path.display()))?;
+            writer.flush()
+                .with_context(|| format!("Failed to write file: {}", tmp_path.display()))?;
+        }
+
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("Failed to replace: {}", path.display()))?;
+
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use encryptor_core::Encryptor;
+use sha2::{Digest, Sha256};
+
+/// Typical filesystem limit on a single path component.
+const MAX_COMPONENT_LEN: usize = 255;
+
+/// Marker prefix for components whose encrypted name overflows
+/// `MAX_COMPONENT_LEN`; the real encrypted name is stashed in a `.name`
+/// sidecar file alongside it, keyed by this prefix plus a hash.
+const LONGNAME_PREFIX: &str = "gocryptfs.longname.";
+
+/// Derive the key used to encrypt file and directory names, domain-separated
+/// from the content key so the two never collide even when built from the
+/// same password and salt.
+pub fn derive_filename_encryptor(password: &str, salt: &[u8]) -> Result<Encryptor> {
+    Encryptor::new_with_password(&format!("{password}\u{0}filenames"), salt)
+        .context("Failed to initialize filename encryptor")
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Encrypt a single path component, returning the name to use on disk.
+/// `dir` is the (encrypted-tree) directory the component will live in,
+/// needed to write a `.name` sidecar if the encrypted name overflows.
+/// `reserved_suffix_len` is how many extra bytes the caller will append to
+/// the returned name (e.g. the leaf component's `.encrypted` suffix) - it
+/// must count towards the `MAX_COMPONENT_LEN` check so the *final* on-disk
+/// name fits, not just the bare encoded ciphertext.
+///
+/// Uses `Encryptor::encrypt_deterministic` rather than the random-nonce
+/// `encrypt`, so the same plaintext component always maps to the same
+/// encrypted name - otherwise two files sharing a parent directory (e.g.
+/// `docs/a.txt` and `docs/b.txt`) would each encrypt `"docs"` to a
+/// different ciphertext and land under two unrelated directories instead
+/// of mirroring the plaintext tree's shape.
+pub fn encode_component(key: &Encryptor, name: &str, dir: &Path, reserved_suffix_len: usize) -> Result<String> {
+    let ciphertext = key.encrypt_deterministic(name.as_bytes()).context("Failed to encrypt filename")?;
+    let encoded = URL_SAFE_NO_PAD.encode(&ciphertext);
+
+    if encoded.len() + reserved_suffix_len <= MAX_COMPONENT_LEN {
+        return Ok(encoded);
+    }
+
+    let hash = Sha256::digest(encoded.as_bytes());
+    let short_name = format!("{}{}", LONGNAME_PREFIX, hex_encode(&hash));
+
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    let sidecar = dir.join(format!("{}.name", short_name));
+    std::fs::write(&sidecar, &encoded)
+        .with_context(|| format!("Failed to write longname sidecar: {}", sidecar.display()))?;
+
+    Ok(short_name)
+}
+
+/// Reverse `encode_component`: `encoded_name` is the on-disk component
+/// (possibly a `gocryptfs.longname.<hash>` placeholder), `dir` is the
+/// encrypted-tree directory it lives in.
+pub fn decode_component(key: &Encryptor, encoded_name: &str, dir: &Path) -> Result<String> {
+    let encoded = if encoded_name.starts_with(LONGNAME_PREFIX) {
+        let sidecar = dir.join(format!("{}.name", encoded_name));
+        std::fs::read_to_string(&sidecar)
+            .with_context(|| format!("Failed to read longname sidecar: {}", sidecar.display()))?
+    } else {
+        encoded_name.to_string()
+    };
+
+    let ciphertext = URL_SAFE_NO_PAD
+        .decode(&encoded)
+        .map_err(|e| anyhow::anyhow!("Invalid encrypted filename: {}", e))?;
+    let name_bytes = key.decrypt(&ciphertext).context("Failed to decrypt filename")?;
+    String::from_utf8(name_bytes).context("Decrypted filename is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key() -> Encryptor {
+        Encryptor::new_with_password("password", &[0u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn round_trips_a_short_name() {
+        let key = test_key();
+        let dir = std::env::temp_dir().join("filenames_test_short");
+        let encoded = encode_component(&key, "passwords.txt", &dir, 0).unwrap();
+        assert_eq!(decode_component(&key, &encoded, &dir).unwrap(), "passwords.txt");
+    }
+
+    #[test]
+    fn encoding_the_same_component_twice_is_deterministic() {
+        // Two files sharing a parent directory (e.g. docs/a.txt and
+        // docs/b.txt) must encrypt "docs" to the same ciphertext, or the
+        // encrypted tree stops mirroring the plaintext tree's shape.
+        let key = test_key();
+        let dir = std::env::temp_dir().join("filenames_test_deterministic");
+        let first = encode_component(&key, "docs", &dir, 0).unwrap();
+        let second = encode_component(&key, "docs", &dir, 0).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn falls_back_to_longname_sidecar_when_encoded_name_overflows() {
+        let key = test_key();
+        let dir = std::env::temp_dir().join("filenames_test_longname");
+        let name = "a".repeat(200);
+        let encoded = encode_component(&key, &name, &dir, 0).unwrap();
+        assert!(encoded.starts_with(LONGNAME_PREFIX));
+        assert_eq!(decode_component(&key, &encoded, &dir).unwrap(), name);
+    }
+
+    #[test]
+    fn reserves_room_for_the_leaf_encrypted_suffix() {
+        // An encoded name that fits in MAX_COMPONENT_LEN on its own but would
+        // overflow once the caller appends ".encrypted" must still take the
+        // longname path, or the resulting on-disk file name exceeds the
+        // filesystem limit the longname feature exists to avoid.
+        let key = test_key();
+        let dir = std::env::temp_dir().join("filenames_test_reserved");
+        let mut name_len = 1;
+        let mut encoded = encode_component(&key, &"a".repeat(name_len), &dir, 0).unwrap();
+        // Walk up in plaintext length until the bare encoding first takes the
+        // longname branch - checked via the prefix, not the returned string's
+        // length, since the longname placeholder itself is short and fixed-size
+        // and would never trip a `len() <= MAX_COMPONENT_LEN` loop condition.
+        while !encoded.starts_with(LONGNAME_PREFIX) {
+            name_len += 1;
+            encoded = encode_component(&key, &"a".repeat(name_len), &dir, 0).unwrap();
+        }
+        let boundary_len = name_len - 1;
+        let boundary_name = "a".repeat(boundary_len);
+
+        let bare = encode_component(&key, &boundary_name, &dir, 0).unwrap();
+        assert!(!bare.starts_with(LONGNAME_PREFIX), "bare encoding should still fit");
+
+        let reserved = encode_component(&key, &boundary_name, &dir, ".encrypted".len()).unwrap();
+        assert!(reserved.starts_with(LONGNAME_PREFIX), "leaf encoding must reserve suffix room");
+    }
+}
@@ -1,5 +1,8 @@
 mod cli;
+mod encryptor_fuse;
 mod file_handler;
+mod filenames;
+mod stego;
 
 use std::path::{Path, PathBuf};
 use anyhow::{Result, Context};
@@ -18,20 +21,32 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
     
     match cli.command {
-        cli::Commands::Encrypt { input, output } => {
-            encrypt_file(&input, output.as_deref())?;
+        cli::Commands::Encrypt { input, output, cipher, argon2_params, stego } => {
+            encrypt_file(&input, output.as_deref(), cipher, argon2_params.as_deref(), stego.as_deref())?;
         }
-        cli::Commands::Decrypt { input, output } => {
-            decrypt_file(&input, output.as_deref())?;
+        cli::Commands::Decrypt { input, output, stego } => {
+            decrypt_file(&input, output.as_deref(), stego)?;
         }
-        cli::Commands::EncryptDir { input, output } => {
-            encrypt_dir(&input, output.as_deref())?;
+        cli::Commands::EncryptDir { input, output, encrypt_names, cipher, argon2_params } => {
+            encrypt_dir(&input, output.as_deref(), encrypt_names, cipher, argon2_params.as_deref())?;
         }
-        cli::Commands::DecryptDir { input, output } => {
-            decrypt_dir(&input, output.as_deref())?;
+        cli::Commands::DecryptDir { input, output, encrypt_names } => {
+            decrypt_dir(&input, output.as_deref(), encrypt_names)?;
+        }
+        cli::Commands::Mount { input, mountpoint } => {
+            mount_dir(&input, &mountpoint)?;
+        }
+        cli::Commands::ChangePassword { input } => {
+            change_password(&input)?;
+        }
+        cli::Commands::Embed { input, carrier, output } => {
+            embed_file(&input, &carrier, output.as_deref())?;
+        }
+        cli::Commands::Extract { input, output } => {
+            extract_file(&input, output.as_deref())?;
         }
     }
-    
+
     Ok(())
 }
 
@@ -47,44 +62,84 @@ fn create_progress_bar(len: u64, msg: &str) -> ProgressBar {
     pb
 }
 
-fn encrypt_file(input_path: &str, output_path: Option<&str>) -> Result<()> {
+/// Parse a `--argon2-params "memory_kib,iterations,parallelism"` value,
+/// falling back to `Argon2Params::default()` when none was given.
+fn parse_argon2_params(spec: Option<&str>) -> Result<encryptor_core::Argon2Params> {
+    let Some(spec) = spec else {
+        return Ok(encryptor_core::Argon2Params::default());
+    };
+    let parts: Vec<&str> = spec.split(',').collect();
+    let [memory_kib, iterations, parallelism] = parts.as_slice() else {
+        anyhow::bail!("Invalid --argon2-params: expected \"memory_kib,iterations,parallelism\"");
+    };
+    Ok(encryptor_core::Argon2Params {
+        memory_kib: memory_kib.trim().parse()
+            .with_context(|| format!("Invalid --argon2-params memory value: {}", memory_kib))?,
+        iterations: iterations.trim().parse()
+            .with_context(|| format!("Invalid --argon2-params iterations value: {}", iterations))?,
+        parallelism: parallelism.trim().parse()
+            .with_context(|| format!("Invalid --argon2-params parallelism value: {}", parallelism))?,
+    })
+}
+
+fn to_cipher_id(cipher: cli::CipherArg) -> encryptor_core::CipherId {
+    match cipher {
+        cli::CipherArg::Aes256Gcm => encryptor_core::CipherId::Aes256Gcm,
+        cli::CipherArg::ChaCha20Poly1305 => encryptor_core::CipherId::ChaCha20Poly1305,
+    }
+}
+
+/// Default output path for a stego image: `<carrier>_stego.png` next to the
+/// carrier. Always `.png`, whatever the carrier's own extension (PNG or
+/// BMP), which keeps the output on a lossless encoder as `stego::embed`
+/// requires.
+fn default_stego_output(carrier: &Path, output_path: Option<&str>) -> PathBuf {
+    output_path.map(PathBuf::from).unwrap_or_else(|| {
+        let stem = carrier.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        carrier.with_file_name(format!("{}_stego.png", stem))
+    })
+}
+
+fn encrypt_file(
+    input_path: &str,
+    output_path: Option<&str>,
+    cipher: cli::CipherArg,
+    argon2_params: Option<&str>,
+    stego: Option<&str>,
+) -> Result<()> {
     // Prompt for password
     let password = prompt_password("Enter password: ")
         .context("Failed to read password")?;
-    
+
     let input_path = Path::new(input_path);
+
+    // Generate a random master key and a salt for the password-derived
+    // key-encryption-key that wraps it, so the password can change later
+    // without re-encrypting the file (see `change-password`).
+    let salt = Encryptor::generate_salt();
+    let master_key = Encryptor::generate_master_key();
+    let cipher_id = to_cipher_id(cipher);
+    let argon2_params = parse_argon2_params(argon2_params)?;
+
+    if let Some(carrier) = stego {
+        let carrier_path = Path::new(carrier);
+        let container = FileHandler::encrypt_to_container_agile(
+            input_path, &password, &salt, &master_key, cipher_id, &argon2_params,
+        )
+        .with_context(|| format!("Failed to encrypt file: {}", input_path.display()))?;
+
+        let output_path = default_stego_output(carrier_path, output_path);
+        stego::embed(carrier_path, &container, &output_path)
+            .with_context(|| format!("Failed to embed into carrier image: {}", carrier_path.display()))?;
+
+        println!("File encrypted and hidden in image: {}", output_path.display());
+        return Ok(());
+    }
+
     let file_size = std::fs::metadata(input_path)
         .with_context(|| format!("Failed to read input file metadata: {}", input_path.display()))?
         .len();
-    
-    let use_progress = file_size >= LARGE_FILE_THRESHOLD;
-    
-    // Read input file (with optional progress bar)
-    let plaintext = if use_progress {
-        let pb = create_progress_bar(file_size, "Reading");
-        let data = FileHandler::read_file_with_progress(input_path, |read, _| {
-            pb.set_position(read);
-        })
-        .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
-        pb.finish_and_clear();
-        data
-    } else {
-        FileHandler::read_file(input_path)
-            .with_context(|| format!("Failed to read input file: {}", input_path.display()))?
-    };
-    
-    // Generate salt and create encryptor
-    let salt = Encryptor::generate_salt();
-    let encryptor = Encryptor::new_with_password(&password, &salt)
-        .context("Failed to initialize encryptor")?;
-    
-    // Encrypt data
-    let encrypted_data = encryptor.encrypt(&plaintext)
-        .context("Encryption failed")?;
-    
-    // Create encrypted file format
-    let encrypted_file = FileHandler::create_encrypted_file(&salt, &encrypted_data);
-    
+
     // Determine output path
     let output_path: PathBuf = output_path
         .map(PathBuf::from)
@@ -93,62 +148,53 @@ fn encrypt_file(input_path: &str, output_path: Option<&str>) -> Result<()> {
             path.set_extension("encrypted");
             path
         });
-    
-    // Write encrypted file (with optional progress bar)
-    if use_progress {
-        let pb = create_progress_bar(encrypted_file.len() as u64, "Writing");
-        FileHandler::write_file_with_progress(&output_path, &encrypted_file, |written, _| {
-            pb.set_position(written);
-        })
-        .with_context(|| format!("Failed to write encrypted file: {}", output_path.display()))?;
+
+    // Stream the file through in fixed-size chunks so memory use stays
+    // bounded regardless of file size.
+    let use_progress = file_size >= LARGE_FILE_THRESHOLD;
+    let pb = use_progress.then(|| create_progress_bar(file_size, "Encrypting"));
+
+    FileHandler::encrypt_file_agile(input_path, &output_path, &password, &salt, &master_key, cipher_id, &argon2_params)
+        .with_context(|| format!("Failed to encrypt file: {}", input_path.display()))?;
+
+    if let Some(pb) = pb {
+        pb.set_position(file_size);
         pb.finish_with_message("Done");
-    } else {
-        FileHandler::write_file(&output_path, &encrypted_file)
-            .with_context(|| format!("Failed to write encrypted file: {}", output_path.display()))?;
     }
-    
+
     println!("File encrypted successfully: {}", output_path.display());
     Ok(())
 }
 
-fn decrypt_file(input_path: &str, output_path: Option<&str>) -> Result<()> {
+fn decrypt_file(input_path: &str, output_path: Option<&str>, stego: bool) -> Result<()> {
     // Prompt for password
     let password = prompt_password("Enter password: ")
         .context("Failed to read password")?;
-    
+
     let input_path = Path::new(input_path);
+
+    if stego {
+        let container = stego::extract(input_path)
+            .with_context(|| format!("Failed to extract from stego image: {}", input_path.display()))?;
+        let plaintext = FileHandler::decrypt_container(&container, &password)
+            .with_context(|| format!("Failed to decrypt file: {}", input_path.display()))?;
+
+        let output_path: PathBuf = output_path.map(PathBuf::from).unwrap_or_else(|| {
+            let mut path = input_path.to_path_buf();
+            path.set_extension("decrypted");
+            path
+        });
+        FileHandler::write_file(&output_path, &plaintext)
+            .with_context(|| format!("Failed to write decrypted file: {}", output_path.display()))?;
+
+        println!("File decrypted successfully: {}", output_path.display());
+        return Ok(());
+    }
+
     let file_size = std::fs::metadata(input_path)
         .with_context(|| format!("Failed to read encrypted file metadata: {}", input_path.display()))?
         .len();
-    
-    let use_progress = file_size >= LARGE_FILE_THRESHOLD;
-    
-    // Read encrypted file (with optional progress bar)
-    let encrypted_file_data = if use_progress {
-        let pb = create_progress_bar(file_size, "Reading");
-        let data = FileHandler::read_file_with_progress(input_path, |read, _| {
-            pb.set_position(read);
-        })
-        .with_context(|| format!("Failed to read encrypted file: {}", input_path.display()))?;
-        pb.finish_and_clear();
-        data
-    } else {
-        FileHandler::read_file(input_path)
-            .with_context(|| format!("Failed to read encrypted file: {}", input_path.display()))?
-    };
-    
-    // Parse encrypted file format
-    let (salt, encrypted_data) = FileHandler::parse_encrypted_file(&encrypted_file_data)
-        .context("Failed to parse encrypted file format")?;
-    
-    // Create decryptor with extracted salt
-    let encryptor = Encryptor::new_with_password(&password, &salt)
-        .context("Failed to initialize decryptor")?;
-    
-    // Decrypt data
-    let plaintext = encryptor.decrypt(&encrypted_data)
-        .context("Decryption failed - wrong password or corrupted file")?;
-    
+
     // Determine output path
     let output_path: PathBuf = output_path.map(PathBuf::from).unwrap_or_else(|| {
         let mut path = input_path.to_path_buf();
@@ -159,20 +205,39 @@ fn decrypt_file(input_path: &str, output_path: Option<&str>) -> Result<()> {
         }
         path
     });
-    
-    // Write decrypted file (with optional progress bar)
-    if use_progress {
-        let pb = create_progress_bar(plaintext.len() as u64, "Writing");
-        FileHandler::write_file_with_progress(&output_path, &plaintext, |written, _| {
-            pb.set_position(written);
-        })
-        .with_context(|| format!("Failed to write decrypted file: {}", output_path.display()))?;
-        pb.finish_with_message("Done");
+
+    let header = FileHandler::read_header(input_path)
+        .context("Failed to parse encrypted file format")?;
+
+    let use_progress = file_size >= LARGE_FILE_THRESHOLD;
+    let pb = use_progress.then(|| create_progress_bar(file_size, "Decrypting"));
+
+    if header.wrapped_master_key.is_some() {
+        FileHandler::decrypt_file_master_keyed(input_path, &output_path, &password)
+            .with_context(|| format!("Failed to decrypt file: {}", input_path.display()))?;
+    } else if header.stream_nonce_prefix.is_some() {
+        FileHandler::decrypt_file_streamed(input_path, &output_path, &password)
+            .with_context(|| format!("Failed to decrypt file: {}", input_path.display()))?;
     } else {
+        // Legacy version-1 (whole-file) container: fall back to the
+        // buffer-based path.
+        let encrypted_file_data = FileHandler::read_file(input_path)
+            .with_context(|| format!("Failed to read encrypted file: {}", input_path.display()))?;
+        let (salt, encrypted_data) = FileHandler::parse_encrypted_file(&encrypted_file_data)
+            .context("Failed to parse encrypted file format")?;
+        let encryptor = Encryptor::new_with_password(&password, &salt)
+            .context("Failed to initialize decryptor")?;
+        let plaintext = encryptor.decrypt(&encrypted_data)
+            .context("Decryption failed - wrong password or corrupted file")?;
         FileHandler::write_file(&output_path, &plaintext)
             .with_context(|| format!("Failed to write decrypted file: {}", output_path.display()))?;
     }
-    
+
+    if let Some(pb) = pb {
+        pb.set_position(file_size);
+        pb.finish_with_message("Done");
+    }
+
     println!("File decrypted successfully: {}", output_path.display());
     Ok(())
 }
@@ -189,7 +254,13 @@ fn collect_files(dir: &Path) -> Result<Vec<PathBuf>> {
     Ok(files)
 }
 
-fn encrypt_dir(input_dir: &str, output_dir: Option<&str>) -> Result<()> {
+fn encrypt_dir(
+    input_dir: &str,
+    output_dir: Option<&str>,
+    encrypt_names: bool,
+    cipher: cli::CipherArg,
+    argon2_params: Option<&str>,
+) -> Result<()> {
     let password = prompt_password("Enter password: ")
         .context("Failed to read password")?;
     
@@ -220,42 +291,62 @@ fn encrypt_dir(input_dir: &str, output_dir: Option<&str>) -> Result<()> {
     );
     pb.set_message("Encrypting directory");
     
+    // One master key protects the whole tree; each file wraps its own copy
+    // under the password so a later password change only rewrites headers.
     let salt = Encryptor::generate_salt();
-    let encryptor = Encryptor::new_with_password(&password, &salt)
-        .context("Failed to initialize encryptor")?;
-    
+    let master_key = Encryptor::generate_master_key();
+    let cipher_id = to_cipher_id(cipher);
+    let argon2_params = parse_argon2_params(argon2_params)?;
+    let name_encryptor = encrypt_names
+        .then(|| filenames::derive_filename_encryptor(&password, &salt))
+        .transpose()?;
+
     for file_path in &files {
         let relative = file_path
             .strip_prefix(input_path)
             .with_context(|| format!("Failed to get relative path: {}", file_path.display()))?;
-        
-        let plaintext = FileHandler::read_file(file_path)
-            .with_context(|| format!("Failed to read: {}", file_path.display()))?;
-        
-        let encrypted_data = encryptor.encrypt(&plaintext)
-            .context("Encryption failed")?;
-        let encrypted_file = FileHandler::create_encrypted_file(&salt, &encrypted_data);
-        
-        let mut out_file = output_path.join(relative);
-        let ext = out_file
-            .extension()
-            .map(|e| format!("{}.encrypted", e.to_string_lossy()))
-            .unwrap_or_else(|| "encrypted".to_string());
-        out_file.set_extension(ext);
-        
+
+        let out_file = if let Some(name_encryptor) = &name_encryptor {
+            let mut out_file = output_path.clone();
+            let components: Vec<_> = relative.components().collect();
+            for (i, component) in components.iter().enumerate() {
+                let name = component.as_os_str().to_string_lossy();
+                let is_leaf = i == components.len() - 1;
+                // The leaf component gets ".encrypted" appended after encoding, so
+                // that suffix must be reserved here or the longname check below
+                // would pass names that overflow MAX_COMPONENT_LEN once appended.
+                let reserved = if is_leaf { ".encrypted".len() } else { 0 };
+                let encoded = filenames::encode_component(name_encryptor, &name, &out_file, reserved)?;
+                out_file = if is_leaf {
+                    out_file.join(format!("{}.encrypted", encoded))
+                } else {
+                    out_file.join(encoded)
+                };
+            }
+            out_file
+        } else {
+            let mut out_file = output_path.join(relative);
+            let ext = out_file
+                .extension()
+                .map(|e| format!("{}.encrypted", e.to_string_lossy()))
+                .unwrap_or_else(|| "encrypted".to_string());
+            out_file.set_extension(ext);
+            out_file
+        };
+
         FileHandler::create_parent_dirs(&out_file)?;
-        FileHandler::write_file(&out_file, &encrypted_file)
-            .with_context(|| format!("Failed to write: {}", out_file.display()))?;
-        
+        FileHandler::encrypt_file_agile(file_path, &out_file, &password, &salt, &master_key, cipher_id, &argon2_params)
+            .with_context(|| format!("Failed to encrypt: {}", file_path.display()))?;
+
         pb.inc(1);
     }
-    
+
     pb.finish_with_message("Done");
     println!("Directory encrypted successfully: {} ({} files)", output_path.display(), files.len());
     Ok(())
 }
 
-fn decrypt_dir(input_dir: &str, output_dir: Option<&str>) -> Result<()> {
+fn decrypt_dir(input_dir: &str, output_dir: Option<&str>, encrypt_names: bool) -> Result<()> {
     let password = prompt_password("Enter password: ")
         .context("Failed to read password")?;
     
@@ -302,31 +393,57 @@ fn decrypt_dir(input_dir: &str, output_dir: Option<&str>) -> Result<()> {
         let relative = file_path
             .strip_prefix(input_path)
             .with_context(|| format!("Failed to get relative path: {}", file_path.display()))?;
-        
-        let encrypted_file_data = FileHandler::read_file(file_path)
-            .with_context(|| format!("Failed to read: {}", file_path.display()))?;
-        
-        let (salt, encrypted_data) = FileHandler::parse_encrypted_file(&encrypted_file_data)
+
+        let header = FileHandler::read_header(file_path)
             .with_context(|| format!("Invalid encrypted file: {}", file_path.display()))?;
-        
-        let encryptor = Encryptor::new_with_password(&password, &salt)
-            .context("Failed to initialize decryptor")?;
-        
-        let plaintext = encryptor.decrypt(&encrypted_data)
-            .with_context(|| format!("Decryption failed (wrong password?): {}", file_path.display()))?;
-        
-        // Strip .encrypted from extension: e.g. a.txt.encrypted -> a.txt
-        let mut out_file = output_path.join(relative);
-        if out_file.extension().and_then(|e| e.to_str()) == Some("encrypted") {
-            out_file.set_extension("");
+
+        let out_file = if encrypt_names {
+            let name_encryptor = filenames::derive_filename_encryptor(&password, &header.salt)?;
+            let components: Vec<_> = relative.components().collect();
+            let mut out_file = output_path.clone();
+            let mut encrypted_dir_so_far = input_path.to_path_buf();
+            for (i, component) in components.iter().enumerate() {
+                let mut name = component.as_os_str().to_string_lossy().to_string();
+                if i == components.len() - 1 {
+                    name = name.strip_suffix(".encrypted").unwrap_or(&name).to_string();
+                }
+                let decoded = filenames::decode_component(&name_encryptor, &name, &encrypted_dir_so_far)
+                    .with_context(|| format!("Failed to decrypt filename: {}", file_path.display()))?;
+                encrypted_dir_so_far = encrypted_dir_so_far.join(component.as_os_str());
+                out_file = out_file.join(decoded);
+            }
+            out_file
         } else {
-            out_file.set_extension("decrypted");
-        }
-        
+            // Strip .encrypted from extension: e.g. a.txt.encrypted -> a.txt
+            let mut out_file = output_path.join(relative);
+            if out_file.extension().and_then(|e| e.to_str()) == Some("encrypted") {
+                out_file.set_extension("");
+            } else {
+                out_file.set_extension("decrypted");
+            }
+            out_file
+        };
         FileHandler::create_parent_dirs(&out_file)?;
-        FileHandler::write_file(&out_file, &plaintext)
-            .with_context(|| format!("Failed to write: {}", out_file.display()))?;
-        
+
+        if header.wrapped_master_key.is_some() {
+            FileHandler::decrypt_file_master_keyed(file_path, &out_file, &password)
+                .with_context(|| format!("Decryption failed (wrong password?): {}", file_path.display()))?;
+        } else if header.stream_nonce_prefix.is_some() {
+            FileHandler::decrypt_file_streamed(file_path, &out_file, &password)
+                .with_context(|| format!("Decryption failed (wrong password?): {}", file_path.display()))?;
+        } else {
+            let encrypted_file_data = FileHandler::read_file(file_path)
+                .with_context(|| format!("Failed to read: {}", file_path.display()))?;
+            let (salt, encrypted_data) = FileHandler::parse_encrypted_file(&encrypted_file_data)
+                .with_context(|| format!("Invalid encrypted file: {}", file_path.display()))?;
+            let encryptor = Encryptor::new_with_password(&password, &salt)
+                .context("Failed to initialize decryptor")?;
+            let plaintext = encryptor.decrypt(&encrypted_data)
+                .with_context(|| format!("Decryption failed (wrong password?): {}", file_path.display()))?;
+            FileHandler::write_file(&out_file, &plaintext)
+                .with_context(|| format!("Failed to write: {}", out_file.display()))?;
+        }
+
         pb.inc(1);
     }
     
@@ -334,3 +451,104 @@ fn decrypt_dir(input_dir: &str, output_dir: Option<&str>) -> Result<()> {
     println!("Directory decrypted successfully: {} ({} files)", output_path.display(), files.len());
     Ok(())
 }
+
+fn mount_dir(input_dir: &str, mountpoint: &str) -> Result<()> {
+    let password = prompt_password("Enter password: ")
+        .context("Failed to read password")?;
+
+    let input_path = Path::new(input_dir);
+    if !input_path.is_dir() {
+        anyhow::bail!("Input is not a directory: {}", input_path.display());
+    }
+
+    let mountpoint = Path::new(mountpoint);
+    if !mountpoint.is_dir() {
+        anyhow::bail!("Mountpoint is not a directory: {}", mountpoint.display());
+    }
+
+    println!(
+        "Mounting {} at {} (unmount with `umount {}` or Ctrl-C)",
+        input_path.display(),
+        mountpoint.display(),
+        mountpoint.display()
+    );
+    encryptor_fuse::mount(input_path, mountpoint, password)
+}
+
+/// Rewrap the master key of `input` (a file or a directory of encrypted
+/// files) under a new password. Content is never re-encrypted: only the
+/// small header carrying the wrapped master key is rewritten.
+fn change_password(input: &str) -> Result<()> {
+    let old_password = prompt_password("Enter current password: ")
+        .context("Failed to read password")?;
+    let new_password = prompt_password("Enter new password: ")
+        .context("Failed to read password")?;
+    let confirm_password = prompt_password("Confirm new password: ")
+        .context("Failed to read password")?;
+    if new_password != confirm_password {
+        anyhow::bail!("New passwords did not match");
+    }
+
+    let input_path = Path::new(input);
+    if input_path.is_dir() {
+        let files = collect_files(input_path)?;
+        let files: Vec<PathBuf> = files
+            .into_iter()
+            .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("encrypted"))
+            .collect();
+
+        if files.is_empty() {
+            println!("No .encrypted files found in directory: {}", input_path.display());
+            return Ok(());
+        }
+
+        for file_path in &files {
+            FileHandler::change_password(file_path, &old_password, &new_password)
+                .with_context(|| format!("Failed to change password: {}", file_path.display()))?;
+        }
+        println!("Password changed successfully for {} files in {}", files.len(), input_path.display());
+    } else {
+        FileHandler::change_password(input_path, &old_password, &new_password)
+            .with_context(|| format!("Failed to change password: {}", input_path.display()))?;
+        println!("Password changed successfully: {}", input_path.display());
+    }
+
+    Ok(())
+}
+
+/// Hide `input` (typically an `ENCR` container produced by `encrypt`) inside
+/// `carrier`'s least-significant bits, giving plausible-deniability storage
+/// where the result looks like an ordinary image.
+fn embed_file(input_path: &str, carrier: &str, output_path: Option<&str>) -> Result<()> {
+    let input_path = Path::new(input_path);
+    let carrier_path = Path::new(carrier);
+
+    let payload = FileHandler::read_file(input_path)
+        .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+
+    let output_path = default_stego_output(carrier_path, output_path);
+    stego::embed(carrier_path, &payload, &output_path)
+        .with_context(|| format!("Failed to embed into carrier image: {}", carrier_path.display()))?;
+
+    println!("File embedded successfully: {}", output_path.display());
+    Ok(())
+}
+
+/// Reverse `embed`: recover the file hidden in a stego image's LSBs.
+fn extract_file(input_path: &str, output_path: Option<&str>) -> Result<()> {
+    let input_path = Path::new(input_path);
+
+    let payload = stego::extract(input_path)
+        .with_context(|| format!("Failed to extract from stego image: {}", input_path.display()))?;
+
+    let output_path: PathBuf = output_path.map(PathBuf::from).unwrap_or_else(|| {
+        let mut path = input_path.to_path_buf();
+        path.set_extension("extracted");
+        path
+    });
+    FileHandler::write_file(&output_path, &payload)
+        .with_context(|| format!("Failed to write extracted file: {}", output_path.display()))?;
+
+    println!("File extracted successfully: {}", output_path.display());
+    Ok(())
+}
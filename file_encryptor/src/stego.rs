@@ -0,0 +1,146 @@
+use std::path::Path;
+use anyhow::{Result, anyhow, Context};
+
+/// Bytes used as a big-endian length prefix ahead of the embedded payload,
+/// so `extract` knows exactly how many bits to read back out.
+const LENGTH_PREFIX_LEN: usize = 4;
+
+/// Extensions `image` will encode without touching pixel values, so embedding
+/// a payload in the LSBs and re-encoding can't silently discard it. Anything
+/// else (JPEG, GIF, ...) is lossy or palette-quantised and must be rejected
+/// up front rather than corrupting the hidden payload at save time.
+const LOSSLESS_EXTENSIONS: &[&str] = &["png", "bmp"];
+
+fn is_lossless(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| LOSSLESS_EXTENSIONS.iter().any(|l| l.eq_ignore_ascii_case(ext)))
+}
+
+/// Hide `payload` in the least-significant bit of every colour channel of
+/// `carrier`, writing the result to `output` as a lossless PNG or BMP.
+/// Plausible deniability: the stego image is, byte for byte, still a valid
+/// image - only off by one least-significant bit per channel from the
+/// original.
+pub fn embed(carrier: &Path, payload: &[u8], output: &Path) -> Result<()> {
+    if !is_lossless(carrier) {
+        return Err(anyhow!(
+            "Carrier image {} must be PNG or BMP: any other format may be re-encoded lossily, \
+             silently destroying the embedded data",
+            carrier.display()
+        ));
+    }
+    if !is_lossless(output) {
+        return Err(anyhow!(
+            "Stego output {} must be PNG or BMP: image::save() picks its encoder from this \
+             extension, and a lossy one would silently destroy the embedded data",
+            output.display()
+        ));
+    }
+
+    let mut image = image::open(carrier)
+        .with_context(|| format!("Failed to open carrier image: {}", carrier.display()))?
+        .to_rgba8();
+
+    let (width, height) = image.dimensions();
+    let bytes_available = (width as usize * height as usize * 4) / 8;
+    let total_len = LENGTH_PREFIX_LEN + payload.len();
+    if total_len > bytes_available {
+        return Err(anyhow!(
+            "Carrier image too small: needs {} bytes of LSB capacity, has {}",
+            total_len,
+            bytes_available
+        ));
+    }
+
+    let mut stream = Vec::with_capacity(total_len);
+    stream.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    stream.extend_from_slice(payload);
+
+    let mut bits = stream.iter().flat_map(|byte| {
+        let byte = *byte;
+        (0..8).rev().map(move |i| (byte >> i) & 1)
+    });
+
+    'embed: for pixel in image.pixels_mut() {
+        for channel in pixel.0.iter_mut() {
+            let Some(bit) = bits.next() else { break 'embed };
+            *channel = (*channel & !1) | bit;
+        }
+    }
+
+    image.save(output)
+        .with_context(|| format!("Failed to write stego image: {}", output.display()))?;
+    Ok(())
+}
+
+/// Reverse `embed`: read the length prefix and payload back out of
+/// `carrier`'s least-significant bits.
+pub fn extract(carrier: &Path) -> Result<Vec<u8>> {
+    let image = image::open(carrier)
+        .with_context(|| format!("Failed to open stego image: {}", carrier.display()))?
+        .to_rgba8();
+
+    let mut bits = image.pixels().flat_map(|pixel| pixel.0.iter().map(|channel| channel & 1));
+
+    let mut len_bytes = [0u8; LENGTH_PREFIX_LEN];
+    for b in len_bytes.iter_mut() {
+        *b = read_bit_byte(&mut bits)?;
+    }
+    let len = u32::from_be_bytes(len_bytes) as usize;
+
+    let mut payload = vec![0u8; len];
+    for b in payload.iter_mut() {
+        *b = read_bit_byte(&mut bits)?;
+    }
+    Ok(payload)
+}
+
+/// Read 8 bits off `bits` and pack them into a byte, most significant bit first.
+fn read_bit_byte(bits: &mut impl Iterator<Item = u8>) -> Result<u8> {
+    let mut byte = 0u8;
+    for _ in 0..8 {
+        let bit = bits.next().ok_or_else(|| anyhow!("Stego image too small to contain a header"))?;
+        byte = (byte << 1) | bit;
+    }
+    Ok(byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn write_png_carrier(path: &Path) {
+        let image = RgbaImage::from_pixel(32, 32, Rgba([128, 128, 128, 255]));
+        image.save(path).unwrap();
+    }
+
+    #[test]
+    fn round_trips_a_payload_through_a_png_carrier() {
+        let carrier = std::env::temp_dir().join("stego_test_carrier.png");
+        let output = std::env::temp_dir().join("stego_test_output.png");
+        write_png_carrier(&carrier);
+
+        let payload = b"ENCRv3 container bytes would go here";
+        embed(&carrier, payload, &output).unwrap();
+        assert_eq!(extract(&output).unwrap(), payload);
+    }
+
+    #[test]
+    fn rejects_a_non_lossless_carrier() {
+        let carrier = std::env::temp_dir().join("stego_test_carrier.jpg");
+        let output = std::env::temp_dir().join("stego_test_output.png");
+        let err = embed(&carrier, b"payload", &output).unwrap_err();
+        assert!(err.to_string().contains("PNG or BMP"));
+    }
+
+    #[test]
+    fn rejects_a_non_lossless_output_extension() {
+        let carrier = std::env::temp_dir().join("stego_test_carrier2.png");
+        let output = std::env::temp_dir().join("stego_test_output2.jpg");
+        write_png_carrier(&carrier);
+        let err = embed(&carrier, b"payload", &output).unwrap_err();
+        assert!(err.to_string().contains("PNG or BMP"));
+    }
+}